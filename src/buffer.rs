@@ -1,13 +1,74 @@
+use ropey::Rope;
 use std::cmp::{self, Ordering};
-use std::io::{self, BufRead, BufReader, Read};
+use std::fs;
+use std::io::{self, Read};
+use unicode_segmentation::UnicodeSegmentation;
 use BufferOp::*;
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct Cursor {
     pub line: usize,
+    // A count of grapheme clusters into the line, not a byte offset.
     pub col: usize,
 }
 
+fn grapheme_len(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+// Char offset into `line` of the start of the `col`-th grapheme cluster, or
+// of the end of the line when `col` sits at or past it.
+fn char_offset(line: &str, col: usize) -> usize {
+    line.graphemes(true)
+        .take(col)
+        .map(|g| g.chars().count())
+        .sum()
+}
+
+// The grapheme cluster at `col`, if any.
+fn grapheme_at(line: &str, col: usize) -> Option<&str> {
+    line.graphemes(true).nth(col)
+}
+
+// The inverse of `char_offset`: the column of the grapheme cluster starting
+// at or before `offset` chars into `line`.
+fn col_for_char_offset(line: &str, offset: usize) -> usize {
+    let mut col = 0;
+    let mut seen = 0;
+
+    for g in line.graphemes(true) {
+        let len = g.chars().count();
+        if seen + len > offset {
+            break;
+        }
+        seen += len;
+        col += 1;
+    }
+
+    col
+}
+
+// Where a cursor sitting at `cur` ends up once `text` has been inserted
+// there, accounting for any line breaks `text` itself contains.
+fn cursor_after_insert(cur: Cursor, text: &str) -> Cursor {
+    let newlines = text.matches('\n').count();
+
+    if newlines == 0 {
+        Cursor {
+            line: cur.line,
+            col: cur.col + grapheme_len(text),
+        }
+    } else {
+        Cursor {
+            line: cur.line + newlines,
+            col: grapheme_len(text.rsplit('\n').next().unwrap_or("")),
+        }
+    }
+}
+
+// Maximum number of killed strings kept around for `yank`/`yank_pop`.
+const KILL_RING_LIMIT: usize = 64;
+
 impl PartialOrd for Cursor {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self.line < other.line {
@@ -24,6 +85,23 @@ impl PartialOrd for Cursor {
     }
 }
 
+#[derive(PartialEq, Clone, Copy)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn classify(ch: char) -> CharClass {
+    if ch.is_whitespace() {
+        CharClass::Whitespace
+    } else if ch.is_alphanumeric() || ch == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
 #[derive(PartialEq, Clone, Copy)]
 enum LineCombination {
     FromStart, // Combined from the start of a line. Probably by pressing backspace.
@@ -33,63 +111,89 @@ enum LineCombination {
 #[derive(PartialEq)]
 enum BufferOp {
     InsertChar(Cursor, char),
-    RemoveChar(Cursor, char, bool),
+    RemoveChar(Cursor, String, bool),
     SplitLine(Cursor),
     CombineLine(Cursor, LineCombination),
+    InsertText(Cursor, String),
+    RemoveText(Cursor, String),
     NoOp,
 }
 
 #[derive(PartialEq)]
 pub struct Buffer {
     pub name: String,
-    lines: Vec<String>,
+    // Where `save` writes the buffer's content; `None` for buffers with
+    // nowhere to save to.
+    path: Option<String>,
+    text: Rope,
     cursor: Cursor,
+    dirty: bool,
     undos: Vec<BufferOp>,
     redos: Vec<BufferOp>,
+    // Most recently killed strings, oldest first; `kill_ring_index` is the
+    // entry `yank` would insert next.
+    kill_ring: Vec<String>,
+    kill_ring_index: usize,
+    // Where the text inserted by the last `yank`/`yank_pop` starts, so a
+    // following `yank_pop` knows what to remove before rotating. Cleared by
+    // any other buffer-mutating call.
+    last_yank: Option<Cursor>,
 }
 
 impl Buffer {
     pub fn empty() -> Buffer {
         Buffer {
             name: "[draft]".to_owned(),
-            lines: vec![String::from("")],
+            path: None,
+            text: Rope::from_str(""),
             cursor: Cursor { line: 0, col: 0 },
+            dirty: false,
             undos: Vec::new(),
             redos: Vec::new(),
+            kill_ring: Vec::new(),
+            kill_ring_index: 0,
+            last_yank: None,
         }
     }
 
-    pub fn new<T: Read>(name: String, read: T) -> io::Result<Buffer> {
-        let reader = BufReader::new(read);
-
-        let lines = reader.lines().collect::<io::Result<Vec<String>>>()?;
+    pub fn new<T: Read>(name: String, path: String, mut read: T) -> io::Result<Buffer> {
+        let mut content = String::new();
+        read.read_to_string(&mut content)?;
 
         Ok(Buffer {
             name,
-            lines,
+            path: Some(path),
+            text: Rope::from_str(&content),
             cursor: Cursor { line: 0, col: 0 },
+            dirty: false,
             undos: Vec::new(),
             redos: Vec::new(),
+            kill_ring: Vec::new(),
+            kill_ring_index: 0,
+            last_yank: None,
         })
     }
 
     fn record_op(&mut self, op: BufferOp) {
+        self.last_yank = None;
         if op != NoOp {
+            self.dirty = true;
             self.undos.push(op);
             self.redos.clear();
         }
     }
 
     pub fn undo(&mut self) {
+        self.last_yank = None;
         if let Some(op) = self.undos.pop() {
             match op {
                 InsertChar(cur, _) => {
                     self.cursor = cur;
                     self.op_remove_at();
                 }
-                RemoveChar(cur, ch, at) => {
+                RemoveChar(cur, ref grapheme, at) => {
                     self.cursor = cur;
-                    self.op_insert_char(ch);
+                    self.insert_grapheme(grapheme);
                     if at {
                         self.cursor = cur;
                     }
@@ -105,13 +209,25 @@ impl Buffer {
                         self.cursor = cur;
                     }
                 }
+                InsertText(cur, ref text) => {
+                    let start = self.char_idx(cur);
+                    let end = start + text.chars().count();
+                    self.text.remove(start..end);
+                    self.cursor = cur;
+                }
+                RemoveText(cur, ref text) => {
+                    self.insert_text(cur, text);
+                    self.cursor = cur;
+                }
                 NoOp => (),
             }
+            self.dirty = true;
             self.redos.push(op);
         }
     }
 
     pub fn redo(&mut self) {
+        self.last_yank = None;
         if let Some(op) = self.redos.pop() {
             match op {
                 InsertChar(cur, ch) => {
@@ -137,24 +253,57 @@ impl Buffer {
                         LineCombination::FromEnd => self.op_remove_at(),
                     };
                 }
+                InsertText(cur, ref text) => {
+                    self.cursor = cur;
+                    self.op_insert_text(text);
+                }
+                RemoveText(cur, ref text) => {
+                    self.cursor = cur;
+                    let start = self.char_idx(cur);
+                    let end = start + text.chars().count();
+                    self.text.remove(start..end);
+                }
                 NoOp => (),
             }
+            self.dirty = true;
             self.undos.push(op);
         }
     }
 
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    // Write the buffer's content back to the path it was opened from.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "buffer has no file to save to")
+        })?;
+
+        fs::write(path, self.text.to_string())?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
     // MOVING AROUND
 
     pub fn move_cursor_up(&mut self, delta: usize) {
         if delta <= self.cursor.line {
             self.cursor.line -= delta;
-            self.cursor.col = cmp::min(self.cursor.col, self.lines[self.cursor.line].len());
+            self.cursor.col = cmp::min(
+                self.cursor.col,
+                grapheme_len(&self.line_string(self.cursor.line)),
+            );
         }
     }
 
     pub fn move_cursor_down(&mut self, delta: usize) {
-        self.cursor.line = cmp::min(self.cursor.line + delta, self.lines.len() - 1);
-        self.cursor.col = cmp::min(self.cursor.col, self.lines[self.cursor.line].len());
+        self.cursor.line = cmp::min(self.cursor.line + delta, self.text.len_lines() - 1);
+        self.cursor.col = cmp::min(
+            self.cursor.col,
+            grapheme_len(&self.line_string(self.cursor.line)),
+        );
     }
 
     pub fn move_cursor_left(&mut self, delta: usize) {
@@ -164,7 +313,7 @@ impl Buffer {
     }
 
     pub fn move_end_of_line(&mut self) {
-        self.cursor.col = self.lines[self.cursor.line].len();
+        self.cursor.col = grapheme_len(&self.line_string(self.cursor.line));
     }
 
     pub fn move_start_of_line(&mut self) {
@@ -172,11 +321,14 @@ impl Buffer {
     }
 
     pub fn move_cursor_right(&mut self, delta: usize) {
-        self.cursor.col = cmp::min(self.cursor.col + delta, self.lines[self.cursor.line].len());
+        self.cursor.col = cmp::min(
+            self.cursor.col + delta,
+            grapheme_len(&self.line_string(self.cursor.line)),
+        );
     }
 
     pub fn go_to_line(&mut self, line: usize) -> Result<(), String> {
-        if line < self.lines.len() && line > 0 {
+        if line < self.text.len_lines() && line > 0 {
             self.cursor.line = line - 1;
             Ok(())
         } else {
@@ -184,6 +336,156 @@ impl Buffer {
         }
     }
 
+    pub fn set_cursor(&mut self, cur: Cursor) {
+        self.cursor = cur;
+    }
+
+    pub fn move_next_word_start(&mut self) {
+        self.cursor = self.next_word_start(self.cursor);
+    }
+
+    pub fn move_prev_word_start(&mut self) {
+        self.cursor = self.prev_word_start(self.cursor);
+    }
+
+    pub fn move_next_word_end(&mut self) {
+        self.cursor = self.next_word_end(self.cursor);
+    }
+
+    // The representative char of the grapheme cluster occupying `cur` (used
+    // only to classify it), or None when `cur` sits at the end of its line.
+    fn char_at(&self, cur: Cursor) -> Option<char> {
+        self.line_string(cur.line)
+            .graphemes(true)
+            .nth(cur.col)
+            .and_then(|g| g.chars().next())
+    }
+
+    // End-of-line (and blank lines) count as whitespace, so a word motion
+    // can cross a line boundary just like it crosses a run of spaces.
+    fn class_at(&self, cur: Cursor) -> CharClass {
+        match self.char_at(cur) {
+            Some(ch) => classify(ch),
+            None => CharClass::Whitespace,
+        }
+    }
+
+    // One grapheme cluster forward, wrapping onto the next line at end-of-line.
+    fn next_pos(&self, cur: Cursor) -> Option<Cursor> {
+        if cur.col < grapheme_len(&self.line_string(cur.line)) {
+            Some(Cursor {
+                line: cur.line,
+                col: cur.col + 1,
+            })
+        } else if cur.line + 1 < self.text.len_lines() {
+            Some(Cursor {
+                line: cur.line + 1,
+                col: 0,
+            })
+        } else {
+            None
+        }
+    }
+
+    // One grapheme cluster backward, wrapping onto the previous line's end.
+    fn prev_pos(&self, cur: Cursor) -> Option<Cursor> {
+        if cur.col > 0 {
+            Some(Cursor {
+                line: cur.line,
+                col: cur.col - 1,
+            })
+        } else if cur.line > 0 {
+            let line = cur.line - 1;
+            Some(Cursor {
+                line,
+                col: grapheme_len(&self.line_string(line)),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn next_word_start(&self, from: Cursor) -> Cursor {
+        let mut cur = from;
+
+        // Skip past the run the cursor is currently sitting in, if any.
+        if let Some(ch) = self.char_at(cur) {
+            let class = classify(ch);
+            while let Some(next) = self.next_pos(cur) {
+                if self.class_at(next) != class {
+                    break;
+                }
+                cur = next;
+            }
+            if let Some(next) = self.next_pos(cur) {
+                cur = next;
+            }
+        }
+
+        // Then skip whitespace to land on the first char of the next run.
+        while self.class_at(cur) == CharClass::Whitespace {
+            match self.next_pos(cur) {
+                Some(next) => cur = next,
+                None => break,
+            }
+        }
+
+        cur
+    }
+
+    fn next_word_end(&self, from: Cursor) -> Cursor {
+        let mut cur = from;
+
+        // Step off the current position so a cursor already at a word's end
+        // advances to the next one, then skip whitespace.
+        if let Some(next) = self.next_pos(cur) {
+            cur = next;
+        }
+        while self.class_at(cur) == CharClass::Whitespace {
+            match self.next_pos(cur) {
+                Some(next) => cur = next,
+                None => return cur,
+            }
+        }
+
+        // Walk to the last character of the run we landed in.
+        let class = self.class_at(cur);
+        while let Some(next) = self.next_pos(cur) {
+            if self.class_at(next) != class {
+                break;
+            }
+            cur = next;
+        }
+
+        cur
+    }
+
+    fn prev_word_start(&self, from: Cursor) -> Cursor {
+        let mut cur = from;
+
+        // Skip whitespace (and line boundaries) behind the cursor.
+        while let Some(prev) = self.prev_pos(cur) {
+            if self.class_at(prev) != CharClass::Whitespace {
+                break;
+            }
+            cur = prev;
+        }
+
+        // Then skip the run behind the cursor, landing on its first char.
+        if let Some(prev) = self.prev_pos(cur) {
+            let class = self.class_at(prev);
+            cur = prev;
+            while let Some(prev) = self.prev_pos(cur) {
+                if self.class_at(prev) != class {
+                    break;
+                }
+                cur = prev;
+            }
+        }
+
+        cur
+    }
+
     // GETTING DATA
 
     pub fn get_cursor(&self) -> Cursor {
@@ -191,7 +493,135 @@ impl Buffer {
     }
 
     pub fn get_line(&self) -> String {
-        self.lines[self.cursor.line].to_owned()
+        self.line_at(self.cursor.line)
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.text.len_lines()
+    }
+
+    pub fn line_at(&self, line: usize) -> String {
+        self.line_string(line)
+    }
+
+    // The text of `line`, without its trailing line terminator.
+    fn line_string(&self, line: usize) -> String {
+        let slice = self.text.line(line);
+        let mut end = slice.len_chars();
+
+        if end > 0 && slice.char(end - 1) == '\n' {
+            end -= 1;
+            if end > 0 && slice.char(end - 1) == '\r' {
+                end -= 1;
+            }
+        }
+
+        slice.slice(0..end).to_string()
+    }
+
+    // The absolute rope char index a grapheme-column cursor points at.
+    fn char_idx(&self, cur: Cursor) -> usize {
+        let line = self.line_string(cur.line);
+        self.text.line_to_char(cur.line) + char_offset(&line, cur.col)
+    }
+
+    // The inverse of `char_idx`: the cursor an absolute rope char index falls
+    // on.
+    fn cursor_at_char_idx(&self, idx: usize) -> Cursor {
+        let line = self.text.char_to_line(idx);
+        let offset = idx - self.text.line_to_char(line);
+
+        Cursor {
+            line,
+            col: col_for_char_offset(&self.line_string(line), offset),
+        }
+    }
+
+    // SEARCHING
+
+    // The first occurrence of `needle` at or after `start`, wrapping around
+    // to the beginning of the buffer if none is found before the end.
+    pub fn find_from(&self, start: Cursor, needle: &str) -> Option<Cursor> {
+        if needle.is_empty() {
+            return None;
+        }
+
+        let start_idx = self.char_idx(start);
+        let total = self.text.len_chars();
+
+        let after = self.text.slice(start_idx..total).to_string();
+        if let Some(byte_idx) = after.find(needle) {
+            return Some(self.cursor_at_char_idx(start_idx + after[..byte_idx].chars().count()));
+        }
+
+        // Include enough past `start` to see a match that begins before it.
+        let end = cmp::min(start_idx + needle.chars().count() - 1, total);
+        let before = self.text.slice(0..end).to_string();
+        if let Some(byte_idx) = before.find(needle) {
+            return Some(self.cursor_at_char_idx(before[..byte_idx].chars().count()));
+        }
+
+        None
+    }
+
+    // The last occurrence of `needle` at or before `start`, wrapping around
+    // to the end of the buffer if none is found before the beginning.
+    fn find_before(&self, start: Cursor, needle: &str) -> Option<Cursor> {
+        if needle.is_empty() {
+            return None;
+        }
+
+        let start_idx = self.char_idx(start);
+        let total = self.text.len_chars();
+
+        // Include enough past `start` to see a match that begins at it.
+        let end = cmp::min(start_idx + needle.chars().count(), total);
+        let before = self.text.slice(0..end).to_string();
+        if let Some(byte_idx) = before.rfind(needle) {
+            return Some(self.cursor_at_char_idx(before[..byte_idx].chars().count()));
+        }
+
+        let after = self.text.slice(start_idx..total).to_string();
+        if let Some(byte_idx) = after.rfind(needle) {
+            return Some(self.cursor_at_char_idx(start_idx + after[..byte_idx].chars().count()));
+        }
+
+        None
+    }
+
+    // Move to the next match after the cursor, wrapping at the end of the
+    // buffer. Returns whether a match was found.
+    pub fn search_next(&mut self, needle: &str) -> bool {
+        let probe = self
+            .next_pos(self.cursor)
+            .unwrap_or(Cursor { line: 0, col: 0 });
+
+        match self.find_from(probe, needle) {
+            Some(pos) => {
+                self.cursor = pos;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Move to the previous match before the cursor, wrapping at the start of
+    // the buffer. Returns whether a match was found.
+    pub fn search_prev(&mut self, needle: &str) -> bool {
+        let last_line = self.text.len_lines() - 1;
+        let end_of_buffer = Cursor {
+            line: last_line,
+            col: grapheme_len(&self.line_string(last_line)),
+        };
+        let probe = self.prev_pos(self.cursor).unwrap_or(end_of_buffer);
+
+        match self.find_before(probe, needle) {
+            Some(pos) => {
+                self.cursor = pos;
+                true
+            }
+            None => false,
+        }
     }
 
     // MUTATION OPERATIONS
@@ -202,14 +632,14 @@ impl Buffer {
     }
 
     fn op_newline(&mut self) -> BufferOp {
-        let new_line = self.lines[self.cursor.line].split_off(self.cursor.col);
+        let idx = self.char_idx(self.cursor);
+        self.text.insert_char(idx, '\n');
+
         let op = SplitLine(self.cursor);
 
         self.cursor.line += 1;
         self.cursor.col = 0;
 
-        self.lines.insert(self.cursor.line, new_line);
-
         op
     }
 
@@ -220,14 +650,24 @@ impl Buffer {
 
     fn op_insert_char(&mut self, ch: char) -> BufferOp {
         let cur = self.cursor;
+        let idx = self.char_idx(cur);
 
-        self.lines[cur.line].insert(cur.col, ch);
+        self.text.insert_char(idx, ch);
         self.cursor.col += 1;
 
         InsertChar(cur, ch)
     }
 
-    // Like a "delete", remove the character under the cursor.
+    // Re-insert a whole grapheme cluster as a single unit, advancing the
+    // cursor by one column. Used to undo a removal.
+    fn insert_grapheme(&mut self, grapheme: &str) {
+        let idx = self.char_idx(self.cursor);
+
+        self.text.insert(idx, grapheme);
+        self.cursor.col += 1;
+    }
+
+    // Like a "delete", remove the grapheme cluster under the cursor.
     // Append the next line to the current line if the cursor
     // is at the end of the line.
     pub fn remove_at(&mut self) {
@@ -236,16 +676,23 @@ impl Buffer {
     }
 
     fn op_remove_at(&mut self) -> BufferOp {
+        let line = self.line_string(self.cursor.line);
+
         // Not end of line
-        if self.cursor.col < self.lines[self.cursor.line].len() {
-            let ch = self.lines[self.cursor.line].remove(self.cursor.col);
-            RemoveChar(self.cursor, ch, true)
+        if let Some(g) = grapheme_at(&line, self.cursor.col) {
+            let g = g.to_owned();
+            let start = self.char_idx(self.cursor);
+            let end = start + g.chars().count();
+            self.text.remove(start..end);
+
+            RemoveChar(self.cursor, g, true)
         } else {
             // End of line
-            if self.cursor.line < (self.lines.len() - 1) {
-                // Not end of file
-                let next_line = self.lines.remove(self.cursor.line + 1);
-                self.lines[self.cursor.line].push_str(next_line.as_str());
+            if self.cursor.line < self.text.len_lines() - 1 {
+                // Not end of file: the char right after the line's content
+                // is the newline joining it to the next one.
+                let start = self.char_idx(self.cursor);
+                self.text.remove(start..start + 1);
 
                 CombineLine(self.cursor, LineCombination::FromEnd)
             } else {
@@ -255,7 +702,7 @@ impl Buffer {
         }
     }
 
-    // Like "backspace", remove the character before the cursor.
+    // Like "backspace", remove the grapheme cluster before the cursor.
     // Where removing the first character of a line, moves the
     // line to the end of the previous line.
     pub fn remove_before(&mut self) {
@@ -266,19 +713,24 @@ impl Buffer {
     fn op_remove_before(&mut self) -> BufferOp {
         if self.cursor.col > 0 {
             self.cursor.col -= 1;
-            let ch = self.lines[self.cursor.line].remove(self.cursor.col);
 
-            RemoveChar(self.cursor, ch, false)
+            let line = self.line_string(self.cursor.line);
+            let g = grapheme_at(&line, self.cursor.col).unwrap().to_owned();
+            let start = self.char_idx(self.cursor);
+            let end = start + g.chars().count();
+            self.text.remove(start..end);
+
+            RemoveChar(self.cursor, g, false)
         } else {
             // Start of line
             if self.cursor.line > 0 {
-                // Not first line
-                let line = self.lines.remove(self.cursor.line);
+                // Not first line: the char right before this line's start is
+                // the newline joining it to the previous one.
+                let idx = self.text.line_to_char(self.cursor.line) - 1;
+                self.text.remove(idx..idx + 1);
 
                 self.cursor.line -= 1;
-                self.cursor.col = self.lines[self.cursor.line].len();
-
-                self.lines[self.cursor.line].push_str(line.as_str());
+                self.cursor.col = grapheme_len(&self.line_string(self.cursor.line));
 
                 CombineLine(self.cursor, LineCombination::FromStart)
             } else {
@@ -287,4 +739,147 @@ impl Buffer {
             }
         }
     }
+
+    // Like Ctrl-Backspace: remove from the cursor back to the start of the
+    // previous word, one character at a time so each step is undoable.
+    pub fn remove_word_before(&mut self) {
+        let target = self.prev_word_start(self.cursor);
+
+        while self.cursor > target {
+            self.remove_before();
+        }
+    }
+
+    // Insert `text` at `at` without moving the cursor. Used as the primitive
+    // behind both `op_insert_text` and undoing a kill.
+    fn insert_text(&mut self, at: Cursor, text: &str) {
+        let idx = self.char_idx(at);
+        self.text.insert(idx, text);
+    }
+
+    fn op_insert_text(&mut self, text: &str) -> BufferOp {
+        let cur = self.cursor;
+        self.insert_text(cur, text);
+        self.cursor = cursor_after_insert(cur, text);
+
+        InsertText(cur, text.to_owned())
+    }
+
+    // Push a freshly killed string onto the ring, dropping the oldest entry
+    // once it grows past `KILL_RING_LIMIT`, and point at it for the next yank.
+    fn push_kill(&mut self, text: String) {
+        self.kill_ring.push(text);
+        if self.kill_ring.len() > KILL_RING_LIMIT {
+            self.kill_ring.remove(0);
+        }
+        self.kill_ring_index = self.kill_ring.len() - 1;
+    }
+
+    fn kill_ring_entry(&self) -> Option<&String> {
+        self.kill_ring.get(self.kill_ring_index)
+    }
+
+    // KILLING
+
+    // Like Ctrl-K in Emacs: remove from the cursor to the end of the line,
+    // or the line terminator itself when already at the end of the line.
+    pub fn kill_line(&mut self) {
+        let op = self.op_kill_line();
+        self.record_op(op);
+    }
+
+    fn op_kill_line(&mut self) -> BufferOp {
+        let cur = self.cursor;
+        let line = self.line_string(cur.line);
+        let end_col = grapheme_len(&line);
+
+        let end = if cur.col < end_col {
+            self.char_idx(Cursor {
+                line: cur.line,
+                col: end_col,
+            })
+        } else if cur.line < self.text.len_lines() - 1 {
+            self.char_idx(cur) + 1
+        } else {
+            return NoOp;
+        };
+
+        let start = self.char_idx(cur);
+        let killed = self.text.slice(start..end).to_string();
+        self.text.remove(start..end);
+        self.push_kill(killed.clone());
+
+        RemoveText(cur, killed)
+    }
+
+    // Remove the whole line the cursor sits on, including its terminator.
+    pub fn kill_whole_line(&mut self) {
+        let op = self.op_kill_whole_line();
+        self.record_op(op);
+    }
+
+    fn op_kill_whole_line(&mut self) -> BufferOp {
+        let cur = Cursor {
+            line: self.cursor.line,
+            col: 0,
+        };
+        let start = self.text.line_to_char(cur.line);
+        let end = start + self.text.line(cur.line).len_chars();
+        let killed = self.text.slice(start..end).to_string();
+        self.text.remove(start..end);
+        self.cursor = cur;
+        self.push_kill(killed.clone());
+
+        RemoveText(cur, killed)
+    }
+
+    // Shared tail of `yank`/`yank_pop`: insert the current kill ring entry at
+    // `start` and remember it so a following `yank_pop` can rotate it out.
+    fn do_yank(&mut self, start: Cursor) {
+        let text = match self.kill_ring_entry() {
+            Some(text) => text.clone(),
+            None => return,
+        };
+
+        let op = self.op_insert_text(&text);
+        self.record_op(op);
+        self.last_yank = Some(start);
+    }
+
+    // Like Ctrl-Y in Emacs: insert the most recently killed text.
+    pub fn yank(&mut self) {
+        let start = self.cursor;
+        self.do_yank(start);
+    }
+
+    // Like Meta-Y in Emacs: only right after a yank, swap the text just
+    // inserted for the previous kill ring entry, rotating through history.
+    pub fn yank_pop(&mut self) {
+        let Some(start) = self.last_yank else {
+            return;
+        };
+        if self.kill_ring.is_empty() {
+            return;
+        }
+        // The cursor must still sit right after the text the last yank
+        // inserted; cursor motion does not clear `last_yank`.
+        let yanked = &self.kill_ring[self.kill_ring_index];
+        if cursor_after_insert(start, yanked) != self.cursor {
+            return;
+        }
+
+        let begin = self.char_idx(start);
+        let end = self.char_idx(self.cursor);
+        self.text.remove(begin..end);
+        self.cursor = start;
+        self.undos.pop();
+
+        self.kill_ring_index = if self.kill_ring_index == 0 {
+            self.kill_ring.len() - 1
+        } else {
+            self.kill_ring_index - 1
+        };
+
+        self.do_yank(start);
+    }
 }
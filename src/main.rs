@@ -1,31 +1,99 @@
 mod buffer;
 mod modal;
 
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, Cursor};
 use crate::modal::Modal;
 
 use crossterm::cursor::MoveTo;
 use crossterm::event::{read, Event, KeyCode, KeyModifiers};
 use crossterm::execute;
 use crossterm::style::Print;
-use crossterm::terminal::{enable_raw_mode, Clear, ClearType};
+use crossterm::terminal::{enable_raw_mode, size, Clear, ClearType};
 use std::convert::TryFrom;
 use std::io::stdout;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 trait AppMode {
     fn draw(&self) -> crossterm::Result<()>;
     fn handle_input(self, event: Event) -> Option<AppState>;
 }
 
+// Number of consecutive times the quit key must be pressed to discard
+// unsaved changes.
+const QUIT_CONFIRM_PRESSES: usize = 3;
+
+// Column a tab character advances the cursor to the next multiple of.
+const TAB_STOP: usize = 4;
+
 #[derive(PartialEq)]
 struct Editor {
     buf: Buffer,
+    scroll: Cursor,
+    // Consecutive quit keypresses seen so far while the buffer is dirty;
+    // reset by any other keypress.
+    quit_presses: usize,
+    // Message from the most recent failed save, shown in the status line
+    // until the next keypress.
+    save_error: Option<String>,
 }
 
 #[derive(PartialEq)]
 struct GoToLineModal {
     buf: Buffer,
+    scroll: Cursor,
+    modal: Modal,
+}
+
+#[derive(PartialEq)]
+struct SearchModal {
+    buf: Buffer,
+    scroll: Cursor,
     modal: Modal,
+    // Cursor position when the search started, restored on Esc.
+    origin: Cursor,
+}
+
+// Number of columns needed to right-align every line number in the gutter.
+fn gutter_width(len_lines: usize) -> usize {
+    len_lines.ilog10() as usize + 1
+}
+
+// Terminal column at which the `col`-th grapheme cluster of `line` starts,
+// accounting for double-width glyphs (e.g. CJK) and tabs expanding to the
+// next `tab_stop` multiple along the way.
+fn display_col(line: &str, col: usize, tab_stop: usize) -> usize {
+    let mut rendered = 0;
+
+    for g in line.graphemes(true).take(col) {
+        rendered += if g == "\t" {
+            tab_stop - (rendered % tab_stop)
+        } else {
+            g.width()
+        };
+    }
+
+    rendered
+}
+
+// The text of `line` as it should be drawn: tabs expanded to spaces up to
+// the next `tab_stop` multiple, everything else untouched.
+fn render_line(line: &str, tab_stop: usize) -> String {
+    let mut rendered = String::new();
+    let mut col = 0;
+
+    for g in line.graphemes(true) {
+        if g == "\t" {
+            let width = tab_stop - (col % tab_stop);
+            rendered.push_str(&" ".repeat(width));
+            col += width;
+        } else {
+            rendered.push_str(g);
+            col += g.width();
+        }
+    }
+
+    rendered
 }
 
 #[derive(PartialEq)]
@@ -37,43 +105,198 @@ struct App<S> {
 enum AppState {
     Editor(App<Editor>),
     GoToLineModal(App<GoToLineModal>),
+    SearchModal(App<SearchModal>),
+}
+
+// Clamp `scroll` so that `buf`'s cursor stays inside a viewport of the given
+// size, scrolling the minimum amount necessary in whichever directions it
+// fell outside. Used both to track the cursor as it moves within the editor
+// and to re-clamp the viewport whenever a modal hands the buffer back,
+// since the cursor may have jumped somewhere the old scroll no longer
+// covers (e.g. "go to line", or a search match off screen).
+fn clamp_scroll(buf: &Buffer, mut scroll: Cursor, cols: u16, rows: u16) -> Cursor {
+    let gutter = gutter_width(buf.len_lines());
+    let text_rows = rows.saturating_sub(1) as usize;
+    // The gutter is followed by a one-column separator.
+    let text_cols = (cols as usize).saturating_sub(gutter + 1);
+    let cur = buf.get_cursor();
+
+    if cur.line < scroll.line {
+        scroll.line = cur.line;
+    } else if text_rows > 0 && cur.line >= scroll.line + text_rows {
+        scroll.line = cur.line + 1 - text_rows;
+    }
+
+    if cur.col < scroll.col {
+        scroll.col = cur.col;
+    } else if text_cols > 0 {
+        // Compare rendered widths, so tabs and wide glyphs count for the
+        // screen columns they take up.
+        let line = buf.line_at(cur.line);
+        let cur_display = display_col(&line, cur.col, TAB_STOP);
+        while scroll.col < cur.col
+            && cur_display - display_col(&line, scroll.col, TAB_STOP) >= text_cols
+        {
+            scroll.col += 1;
+        }
+    }
+
+    scroll
+}
+
+impl Editor {
+    // Keep the cursor inside the viewport, scrolling the minimum amount
+    // necessary in whichever directions it ran off screen.
+    fn adjust_scroll(&mut self, cols: u16, rows: u16) {
+        self.scroll = clamp_scroll(&self.buf, self.scroll, cols, rows);
+    }
+}
+
+// Paint the gutter and visible slice of `buf`, starting from `scroll`, into
+// the top `text_rows` rows of the screen. Shared by the editor itself and
+// by any modal that keeps the buffer on screen behind its prompt.
+fn draw_buffer_rows(
+    buf: &Buffer,
+    scroll: Cursor,
+    text_rows: u16,
+    gutter: usize,
+) -> crossterm::Result<()> {
+    for row in 0..text_rows {
+        let line = row as usize + scroll.line;
+        if line >= buf.len_lines() {
+            break;
+        }
+
+        let raw_line = buf.line_at(line);
+        let scroll_col = display_col(&raw_line, scroll.col, TAB_STOP);
+        // `scroll_col` is a display width, so skip by width, not by count.
+        let mut skipped = 0;
+        let text: String = render_line(&raw_line, TAB_STOP)
+            .graphemes(true)
+            .skip_while(|g| {
+                let before = skipped;
+                skipped += g.width();
+                before < scroll_col
+            })
+            .collect();
+
+        execute!(
+            stdout(),
+            MoveTo(0, row),
+            Print(format!("{:>width$} ", line + 1, width = gutter)),
+            Print(text),
+        )?;
+    }
+
+    Ok(())
+}
+
+// Screen column and row the cursor should be drawn at, given `scroll`.
+fn cursor_screen_pos(buf: &Buffer, scroll: Cursor, gutter: usize) -> (u16, u16) {
+    let cur = buf.get_cursor();
+    let cur_line = buf.line_at(cur.line);
+    let cur_display_col = display_col(&cur_line, cur.col, TAB_STOP)
+        .saturating_sub(display_col(&cur_line, scroll.col, TAB_STOP));
+
+    (
+        u16::try_from(gutter + 1 + cur_display_col).unwrap(),
+        u16::try_from(cur.line.saturating_sub(scroll.line)).unwrap(),
+    )
 }
 
 impl AppMode for App<Editor> {
     fn draw(&self) -> crossterm::Result<()> {
-        let line = self.mode.buf.get_line();
-        let cur = self.mode.buf.get_cursor();
+        let buf = &self.mode.buf;
+        let scroll = self.mode.scroll;
+        let (_, rows) = size()?;
+        let text_rows = rows.saturating_sub(1);
+        let gutter = gutter_width(buf.len_lines());
+
+        execute!(stdout(), Clear(ClearType::All))?;
+        draw_buffer_rows(buf, scroll, text_rows, gutter)?;
+
+        let cur = buf.get_cursor();
+        let status_row = rows.saturating_sub(1);
+        let (cursor_col, cursor_row) = cursor_screen_pos(buf, scroll, gutter);
+
+        let mut name_status = buf.name.clone();
+        if buf.is_dirty() {
+            name_status.push_str(" *");
+        }
+        if self.mode.quit_presses > 0 {
+            let remaining = QUIT_CONFIRM_PRESSES - self.mode.quit_presses;
+            name_status.push_str(&format!(
+                "  Unsaved changes, press Esc {} more time{} to quit",
+                remaining,
+                if remaining == 1 { "" } else { "s" },
+            ));
+        }
+        if let Some(err) = &self.mode.save_error {
+            name_status.push_str(&format!("  Couldn't save: {}", err));
+        }
 
         execute!(
             stdout(),
-            MoveTo(0, 3),
-            Clear(ClearType::CurrentLine),
+            MoveTo(0, status_row),
             Print(format!("{}, {}", cur.line + 1, cur.col + 1)),
-            MoveTo(12, 3),
-            Print(self.mode.buf.name.clone()),
-            MoveTo(0, 0),
-            Clear(ClearType::CurrentLine),
-            Print(line),
-            MoveTo(u16::try_from(cur.col).unwrap(), 0),
+            MoveTo(12, status_row),
+            Print(name_status),
+            MoveTo(cursor_col, cursor_row),
         )
     }
 
     fn handle_input(mut self, event: Event) -> Option<AppState> {
+        if !matches!(event, Event::Key(key) if key.code == KeyCode::Esc) {
+            self.mode.quit_presses = 0;
+        }
+
         let buf = &mut self.mode.buf;
+        let mut save_error = None;
 
         match event {
             Event::Key(event) => match event.modifiers {
                 KeyModifiers::CONTROL => match event.code {
                     // Go to line
                     KeyCode::Char('g') => return Some(AppState::GoToLineModal(self.into())),
-                    // Undo
+                    // Incremental search
+                    KeyCode::Char('f') => return Some(AppState::SearchModal(self.into())),
+                    // Undo / redo
                     KeyCode::Char('z') => buf.undo(),
-                    // Redo
-                    KeyCode::Char('y') => buf.redo(),
+                    KeyCode::Char('r') => buf.redo(),
+                    // Kill ring
+                    KeyCode::Char('k') => buf.kill_line(),
+                    KeyCode::Char('u') => buf.kill_whole_line(),
+                    KeyCode::Char('y') => buf.yank(),
+                    // Save
+                    KeyCode::Char('s') => {
+                        if let Err(err) = buf.save() {
+                            save_error = Some(err.to_string());
+                        }
+                    }
+                    // Word-wise motion
+                    KeyCode::Left => buf.move_prev_word_start(),
+                    KeyCode::Right => buf.move_next_word_start(),
+                    KeyCode::Backspace => buf.remove_word_before(),
+                    _ => (),
+                },
+                KeyModifiers::ALT => match event.code {
+                    // Rotate through the kill ring after a yank
+                    KeyCode::Char('y') => buf.yank_pop(),
+                    // Word-wise motion
+                    KeyCode::Right => buf.move_next_word_end(),
                     _ => (),
                 },
                 _ => match event.code {
-                    KeyCode::Esc => return None,
+                    KeyCode::Esc => {
+                        if buf.is_dirty() {
+                            self.mode.quit_presses += 1;
+                            if self.mode.quit_presses >= QUIT_CONFIRM_PRESSES {
+                                return None;
+                            }
+                        } else {
+                            return None;
+                        }
+                    }
                     KeyCode::Enter => buf.newline(),
                     KeyCode::Up => buf.move_cursor_up(1),
                     KeyCode::Down => buf.move_cursor_down(1),
@@ -90,6 +313,11 @@ impl AppMode for App<Editor> {
             _ => (),
         };
 
+        self.mode.save_error = save_error;
+
+        let (cols, rows) = size().unwrap();
+        self.mode.adjust_scroll(cols, rows);
+
         Some(AppState::Editor(self))
     }
 }
@@ -103,7 +331,10 @@ impl AppMode for App<GoToLineModal> {
             MoveTo(0, 0),
             Clear(ClearType::CurrentLine),
             Print(format!("{}: {}", modal.name, modal.line)),
-            MoveTo(u16::try_from(modal.name.len() + 2 + modal.col).unwrap(), 0),
+            MoveTo(
+                u16::try_from(modal.name.width() + 2 + modal.col).unwrap(),
+                0,
+            ),
         )
     }
 
@@ -129,8 +360,18 @@ impl AppMode for App<GoToLineModal> {
 
 impl From<App<GoToLineModal>> for App<Editor> {
     fn from(val: App<GoToLineModal>) -> App<Editor> {
+        // The cursor may have jumped outside of the scroll region carried
+        // over from before the modal opened, so re-clamp the viewport.
+        let (cols, rows) = size().unwrap();
+        let scroll = clamp_scroll(&val.mode.buf, val.mode.scroll, cols, rows);
+
         App {
-            mode: Editor { buf: val.mode.buf },
+            mode: Editor {
+                buf: val.mode.buf,
+                scroll,
+                quit_presses: 0,
+                save_error: None,
+            },
         }
     }
 }
@@ -140,12 +381,107 @@ impl From<App<Editor>> for App<GoToLineModal> {
         App {
             mode: GoToLineModal {
                 buf: val.mode.buf,
+                scroll: val.mode.scroll,
                 modal: Modal::new("Go to line".to_owned()),
             },
         }
     }
 }
 
+impl AppMode for App<SearchModal> {
+    fn draw(&self) -> crossterm::Result<()> {
+        let buf = &self.mode.buf;
+        let scroll = self.mode.scroll;
+        let modal = &self.mode.modal;
+        let (_, rows) = size()?;
+        let text_rows = rows.saturating_sub(1);
+        let gutter = gutter_width(buf.len_lines());
+
+        execute!(stdout(), Clear(ClearType::All))?;
+        draw_buffer_rows(buf, scroll, text_rows, gutter)?;
+
+        // Draw the prompt over the buffer's top row, then leave the
+        // terminal cursor on the live match so it's visible moving as the
+        // query changes, rather than on the prompt text.
+        let (cursor_col, cursor_row) = cursor_screen_pos(buf, scroll, gutter);
+
+        execute!(
+            stdout(),
+            MoveTo(0, 0),
+            Clear(ClearType::CurrentLine),
+            Print(format!("{}: {}", modal.name, modal.line)),
+            MoveTo(cursor_col, cursor_row),
+        )
+    }
+
+    fn handle_input(mut self, event: Event) -> Option<AppState> {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => {
+                    let origin = self.mode.origin;
+                    self.mode.buf.set_cursor(origin);
+                    return Some(AppState::Editor(self.into()));
+                }
+                KeyCode::Enter => return Some(AppState::Editor(self.into())),
+                KeyCode::Down => {
+                    self.mode.buf.search_next(&self.mode.modal.line);
+                }
+                KeyCode::Up => {
+                    self.mode.buf.search_prev(&self.mode.modal.line);
+                }
+                _ => {
+                    handle_modal_input(&mut self.mode.modal, event);
+
+                    let origin = self.mode.origin;
+                    let needle = self.mode.modal.line.clone();
+                    let pos = self.mode.buf.find_from(origin, &needle).unwrap_or(origin);
+                    self.mode.buf.set_cursor(pos);
+                }
+            },
+            _ => (),
+        };
+
+        let (cols, rows) = size().unwrap();
+        self.mode.scroll = clamp_scroll(&self.mode.buf, self.mode.scroll, cols, rows);
+
+        Some(AppState::SearchModal(self))
+    }
+}
+
+impl From<App<SearchModal>> for App<Editor> {
+    fn from(val: App<SearchModal>) -> App<Editor> {
+        // The cursor may have landed on a match outside of the scroll
+        // region carried over from before the search opened, so re-clamp
+        // the viewport.
+        let (cols, rows) = size().unwrap();
+        let scroll = clamp_scroll(&val.mode.buf, val.mode.scroll, cols, rows);
+
+        App {
+            mode: Editor {
+                buf: val.mode.buf,
+                scroll,
+                quit_presses: 0,
+                save_error: None,
+            },
+        }
+    }
+}
+
+impl From<App<Editor>> for App<SearchModal> {
+    fn from(val: App<Editor>) -> App<SearchModal> {
+        let origin = val.mode.buf.get_cursor();
+
+        App {
+            mode: SearchModal {
+                buf: val.mode.buf,
+                scroll: val.mode.scroll,
+                modal: Modal::new("Find".to_owned()),
+                origin,
+            },
+        }
+    }
+}
+
 fn handle_modal_input(modal: &mut Modal, event: Event) {
     match event {
         Event::Key(event) => match event.code {
@@ -166,6 +502,7 @@ fn draw_screen(state: &AppState) -> crossterm::Result<()> {
     match state {
         AppState::Editor(app) => app.draw(),
         AppState::GoToLineModal(app) => app.draw(),
+        AppState::SearchModal(app) => app.draw(),
     }
 }
 
@@ -173,6 +510,7 @@ fn handle_input(state: AppState, event: Event) -> Option<AppState> {
     match state {
         AppState::Editor(app) => app.handle_input(event),
         AppState::GoToLineModal(app) => app.handle_input(event),
+        AppState::SearchModal(app) => app.handle_input(event),
     }
 }
 
@@ -210,12 +548,18 @@ fn app_loop(mut state: AppState) -> crossterm::Result<()> {
 }
 
 fn main() {
-    let here = std::fs::File::open("./src/main.rs").unwrap();
+    let path = "./src/main.rs".to_owned();
+    let here = std::fs::File::open(&path).unwrap();
 
-    let buffer = Buffer::new("[draft]".to_owned(), here).unwrap();
+    let buffer = Buffer::new("[draft]".to_owned(), path, here).unwrap();
 
     let app = AppState::Editor(App::<Editor> {
-        mode: Editor { buf: buffer },
+        mode: Editor {
+            buf: buffer,
+            scroll: Cursor { line: 0, col: 0 },
+            quit_presses: 0,
+            save_error: None,
+        },
     });
 
     enable_raw_mode().unwrap();